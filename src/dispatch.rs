@@ -0,0 +1,16 @@
+//! Per-object event dispatch.
+//!
+//! Instead of hardcoding application behavior into `process_message`,
+//! callers register a [`Dispatch`] implementation against an object id;
+//! `WaylandClient` decodes each incoming event and routes it to whatever
+//! handler owns that object.
+
+use crate::wire::Argument;
+use crate::WaylandClient;
+
+/// Receives decoded events for one object. Register an implementation
+/// with [`WaylandClient::set_handler`] to be called whenever that object
+/// id receives an event.
+pub trait Dispatch {
+    fn event(&mut self, client: &mut WaylandClient, object_id: u32, opcode: u16, args: &[Argument]);
+}
@@ -0,0 +1,50 @@
+//! Error types surfaced while talking to the compositor.
+
+use std::fmt;
+use std::io;
+
+/// A protocol error reported by the compositor via `wl_display::error`:
+/// the object that misbehaved, a compositor-defined error code, and a
+/// human-readable message.
+#[derive(Debug)]
+pub struct WaylandError {
+    pub object_id: u32,
+    pub code: u32,
+    pub message: String,
+}
+
+impl fmt::Display for WaylandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "compositor reported protocol error on object {}: code {} ({})",
+            self.object_id, self.code, self.message
+        )
+    }
+}
+
+impl std::error::Error for WaylandError {}
+
+/// Errors that can occur while sending requests or processing events.
+#[derive(Debug)]
+pub enum ClientError {
+    Io(io::Error),
+    Protocol(WaylandError),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Io(e) => write!(f, "{}", e),
+            ClientError::Protocol(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<io::Error> for ClientError {
+    fn from(e: io::Error) -> Self {
+        ClientError::Io(e)
+    }
+}
@@ -1,12 +1,19 @@
-use std::collections::HashMap;
+mod dispatch;
+mod error;
+mod protocol;
+mod transport;
+mod wire;
+
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::fs::File;
-use std::io::{self, Read, Write};
+use std::io::{self, Read};
 use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
 use std::path::Path;
 
-const WL_DISPLAY_SYNC: u16 = 0;
-const WL_DISPLAY_GET_REGISTRY: u16 = 1;
+use dispatch::Dispatch;
+use error::{ClientError, WaylandError};
+use wire::{Argument, Message};
 
 const DISPLAY_ID: u32 = 1;
 
@@ -14,7 +21,12 @@ struct WaylandClient {
     stream: File,
     objects: HashMap<u32, String>,
     next_id: u32,
+    free_ids: Vec<u32>,
+    registry_id: Option<u32>,
     registry_interfaces: HashMap<u32, (String, u32)>,
+    configure_serials: HashMap<u32, u32>,
+    completed_callbacks: HashSet<u32>,
+    handlers: HashMap<u32, Box<dyn Dispatch>>,
 }
 
 impl WaylandClient {
@@ -26,11 +38,22 @@ impl WaylandClient {
             stream,
             objects,
             next_id: 2,
+            free_ids: Vec::new(),
+            registry_id: None,
             registry_interfaces: HashMap::new(),
+            configure_serials: HashMap::new(),
+            completed_callbacks: HashSet::new(),
+            handlers: HashMap::new(),
         }
     }
 
+    /// Allocates a new object id, reusing one freed by a prior
+    /// `wl_display::delete_id` event before minting a new one.
     fn next_object_id(&mut self) -> u32 {
+        if let Some(id) = self.free_ids.pop() {
+            return id;
+        }
+
         let id = self.next_id;
         self.next_id += 1;
         id
@@ -39,22 +62,12 @@ impl WaylandClient {
     fn send_sync(&mut self) -> io::Result<u32> {
         let callback_id = self.next_object_id();
 
-        let mut msg = vec![
-            DISPLAY_ID.to_ne_bytes()[0],
-            DISPLAY_ID.to_ne_bytes()[1],
-            DISPLAY_ID.to_ne_bytes()[2],
-            DISPLAY_ID.to_ne_bytes()[3],
-            12,
-            0,
-            0,
-            0,
-            callback_id.to_ne_bytes()[0],
-            callback_id.to_ne_bytes()[1],
-            callback_id.to_ne_bytes()[2],
-            callback_id.to_ne_bytes()[3],
-        ];
-
-        self.stream.write_all(&msg)?;
+        let msg = Message::new(
+            DISPLAY_ID,
+            protocol::WL_DISPLAY_SYNC,
+            vec![Argument::NewId(callback_id)],
+        );
+        transport::send_message(&mut self.stream, &msg)?;
         self.objects.insert(callback_id, "wl_callback".to_string());
 
         Ok(callback_id)
@@ -63,87 +76,344 @@ impl WaylandClient {
     fn get_registry(&mut self) -> io::Result<u32> {
         let registry_id = self.next_object_id();
 
-        let mut msg = vec![
-            DISPLAY_ID.to_ne_bytes()[0],
-            DISPLAY_ID.to_ne_bytes()[1],
-            DISPLAY_ID.to_ne_bytes()[2],
-            DISPLAY_ID.to_ne_bytes()[3],
-            (12 | (WL_DISPLAY_GET_REGISTRY as u32) << 16).to_ne_bytes()[0],
-            (12 | (WL_DISPLAY_GET_REGISTRY as u32) << 16).to_ne_bytes()[1],
-            (12 | (WL_DISPLAY_GET_REGISTRY as u32) << 16).to_ne_bytes()[2],
-            (12 | (WL_DISPLAY_GET_REGISTRY as u32) << 16).to_ne_bytes()[3],
-            registry_id.to_ne_bytes()[0],
-            registry_id.to_ne_bytes()[1],
-            registry_id.to_ne_bytes()[2],
-            registry_id.to_ne_bytes()[3],
-        ];
-
-        self.stream.write_all(&msg)?;
+        let msg = Message::new(
+            DISPLAY_ID,
+            protocol::WL_DISPLAY_GET_REGISTRY,
+            vec![Argument::NewId(registry_id)],
+        );
+        transport::send_message(&mut self.stream, &msg)?;
         self.objects.insert(registry_id, "wl_registry".to_string());
+        self.registry_id = Some(registry_id);
 
         Ok(registry_id)
     }
 
-    fn process_message(&mut self) -> io::Result<bool> {
-        let mut header = [0u8; 8];
-        if let Err(e) = self.stream.read_exact(&mut header) {
-            if e.kind() == io::ErrorKind::UnexpectedEof {
-                return Ok(false);
+    /// Sends `wl_registry::bind`, binding the global advertised under
+    /// `name` to a freshly allocated object implementing `interface` at
+    /// `version`, and returns that object's id.
+    fn bind(&mut self, name: u32, interface: &str, version: u32) -> io::Result<u32> {
+        let registry_id = self
+            .registry_id
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "wl_registry not yet obtained"))?;
+        let new_id = self.next_object_id();
+
+        let msg = Message::new(
+            registry_id,
+            protocol::WL_REGISTRY_BIND,
+            vec![
+                Argument::Uint(name),
+                Argument::Str(interface.to_string()),
+                Argument::Uint(version),
+                Argument::NewId(new_id),
+            ],
+        );
+        transport::send_message(&mut self.stream, &msg)?;
+        self.objects.insert(new_id, interface.to_string());
+
+        Ok(new_id)
+    }
+
+    /// Sends `wl_shm::create_pool` on the already-bound `wl_shm` object
+    /// `shm_id`, handing the compositor shared-memory access through `fd`.
+    /// Returns the id of the new `wl_shm_pool` object.
+    fn create_shm_pool(&mut self, shm_id: u32, fd: RawFd, size: i32) -> io::Result<u32> {
+        let pool_id = self.next_object_id();
+
+        let msg = Message::new(
+            shm_id,
+            protocol::WL_SHM_CREATE_POOL,
+            vec![
+                Argument::NewId(pool_id),
+                Argument::Fd(fd),
+                Argument::Int(size),
+            ],
+        );
+        transport::send_message(&mut self.stream, &msg)?;
+        self.objects.insert(pool_id, "wl_shm_pool".to_string());
+
+        Ok(pool_id)
+    }
+
+    /// Sends `wl_compositor::create_surface` on the bound `wl_compositor`
+    /// object `compositor_id` and returns the new `wl_surface` id.
+    fn create_surface(&mut self, compositor_id: u32) -> io::Result<u32> {
+        let surface_id = self.next_object_id();
+
+        let msg = Message::new(
+            compositor_id,
+            protocol::WL_COMPOSITOR_CREATE_SURFACE,
+            vec![Argument::NewId(surface_id)],
+        );
+        transport::send_message(&mut self.stream, &msg)?;
+        self.objects.insert(surface_id, "wl_surface".to_string());
+
+        Ok(surface_id)
+    }
+
+    /// Sends `xdg_wm_base::get_xdg_surface` on the bound `xdg_wm_base`
+    /// object `wm_base_id`, wrapping `surface_id`, and returns the new
+    /// `xdg_surface` id.
+    fn get_xdg_surface(&mut self, wm_base_id: u32, surface_id: u32) -> io::Result<u32> {
+        let xdg_surface_id = self.next_object_id();
+
+        let msg = Message::new(
+            wm_base_id,
+            protocol::XDG_WM_BASE_GET_XDG_SURFACE,
+            vec![Argument::NewId(xdg_surface_id), Argument::Object(surface_id)],
+        );
+        transport::send_message(&mut self.stream, &msg)?;
+        self.objects.insert(xdg_surface_id, "xdg_surface".to_string());
+
+        Ok(xdg_surface_id)
+    }
+
+    /// Sends `xdg_surface::get_toplevel` on `xdg_surface_id` and returns
+    /// the new `xdg_toplevel` id.
+    fn get_toplevel(&mut self, xdg_surface_id: u32) -> io::Result<u32> {
+        let toplevel_id = self.next_object_id();
+
+        let msg = Message::new(
+            xdg_surface_id,
+            protocol::XDG_SURFACE_GET_TOPLEVEL,
+            vec![Argument::NewId(toplevel_id)],
+        );
+        transport::send_message(&mut self.stream, &msg)?;
+        self.objects.insert(toplevel_id, "xdg_toplevel".to_string());
+
+        Ok(toplevel_id)
+    }
+
+    /// Sends `xdg_surface::ack_configure`, acknowledging `serial` on
+    /// `xdg_surface_id` so the compositor knows the pending configure has
+    /// been applied.
+    fn ack_configure(&mut self, xdg_surface_id: u32, serial: u32) -> io::Result<()> {
+        let msg = Message::new(
+            xdg_surface_id,
+            protocol::XDG_SURFACE_ACK_CONFIGURE,
+            vec![Argument::Uint(serial)],
+        );
+        transport::send_message(&mut self.stream, &msg)
+    }
+
+    /// Returns the most recent `configure` serial recorded for
+    /// `xdg_surface_id`, so the caller knows it's safe to commit the
+    /// surface.
+    fn configure_serial(&self, xdg_surface_id: u32) -> Option<u32> {
+        self.configure_serials.get(&xdg_surface_id).copied()
+    }
+
+    /// Sends `wl_seat::get_pointer` on the bound `wl_seat` object
+    /// `seat_id` and returns the new `wl_pointer` id.
+    fn get_pointer(&mut self, seat_id: u32) -> io::Result<u32> {
+        let pointer_id = self.next_object_id();
+
+        let msg = Message::new(
+            seat_id,
+            protocol::WL_SEAT_GET_POINTER,
+            vec![Argument::NewId(pointer_id)],
+        );
+        transport::send_message(&mut self.stream, &msg)?;
+        self.objects.insert(pointer_id, "wl_pointer".to_string());
+
+        Ok(pointer_id)
+    }
+
+    /// Sends `wl_seat::get_keyboard` on the bound `wl_seat` object
+    /// `seat_id` and returns the new `wl_keyboard` id.
+    fn get_keyboard(&mut self, seat_id: u32) -> io::Result<u32> {
+        let keyboard_id = self.next_object_id();
+
+        let msg = Message::new(
+            seat_id,
+            protocol::WL_SEAT_GET_KEYBOARD,
+            vec![Argument::NewId(keyboard_id)],
+        );
+        transport::send_message(&mut self.stream, &msg)?;
+        self.objects.insert(keyboard_id, "wl_keyboard".to_string());
+
+        Ok(keyboard_id)
+    }
+
+    /// Looks up `interface` among the globals seen so far and binds it at
+    /// `min(server_version, max_supported)`.
+    fn bind_global(&mut self, interface: &str, max_supported: u32) -> io::Result<u32> {
+        let (name, server_version) = self
+            .registry_interfaces
+            .iter()
+            .find(|(_, (iface, _))| iface == interface)
+            .map(|(&name, &(_, version))| (name, version))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no global advertised for interface {}", interface),
+                )
+            })?;
+
+        self.bind(name, interface, server_version.min(max_supported))
+    }
+
+    /// Registers `handler` to receive future decoded events for
+    /// `object_id`, replacing any handler already registered for it.
+    fn set_handler(&mut self, object_id: u32, handler: Box<dyn Dispatch>) {
+        self.handlers.insert(object_id, handler);
+    }
+
+    /// Sends `wl_display::sync` and pumps [`Self::process_message`] until
+    /// the matching `wl_callback::done` event arrives, so callers wait for
+    /// exactly the replies they asked for instead of a fixed read count.
+    fn roundtrip(&mut self) -> Result<(), ClientError> {
+        let callback_id = self.send_sync()?;
+
+        while !self.completed_callbacks.remove(&callback_id) {
+            if !self.process_message()? {
+                break;
             }
-            return Err(e);
         }
 
+        Ok(())
+    }
+
+    /// Reads one message off the wire.
+    ///
+    /// Where a message's `Fd` arguments show up depends on the transport:
+    /// on a Unix domain socket they ride as `SCM_RIGHTS` ancillary data
+    /// attached to the *header* bytes, since [`transport::send_message`]
+    /// writes the whole encoded message in a single `sendmsg` call; on
+    /// Redox's `chan` scheme they arrive as `dup`s sent *after* the full
+    /// message body, mirroring the write order in
+    /// [`transport::send_message`]'s Redox branch. The header/body reads
+    /// below ask the transport for out-of-band fds at whichever point
+    /// actually carries them for the current platform.
+    fn process_message(&mut self) -> Result<bool, ClientError> {
+        #[cfg(not(target_os = "redox"))]
+        let (header, header_fds) =
+            match transport::recv_with_fds(&mut self.stream, 8, transport::MAX_FDS_PER_MESSAGE) {
+                Ok(v) => v,
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+                Err(e) => return Err(e.into()),
+            };
+
+        #[cfg(target_os = "redox")]
+        let header = {
+            let mut header = [0u8; 8];
+            if let Err(e) = self.stream.read_exact(&mut header) {
+                if e.kind() == io::ErrorKind::UnexpectedEof {
+                    return Ok(false);
+                }
+                return Err(e.into());
+            }
+            header
+        };
+
         let obj_id = u32::from_ne_bytes([header[0], header[1], header[2], header[3]]);
         let size_opcode = u32::from_ne_bytes([header[4], header[5], header[6], header[7]]);
         let size = size_opcode >> 16;
         let opcode = (size_opcode & 0xFFFF) as u16;
 
         let body_size = size as usize - 8;
-        let mut body = vec![0u8; body_size];
-        self.stream.read_exact(&mut body)?;
 
-        let obj_type = self.objects.get(&obj_id).cloned();
+        let interface = self.objects.get(&obj_id).cloned();
+        let desc = interface
+            .as_deref()
+            .and_then(|interface| protocol::event_desc(interface, opcode));
+
+        #[cfg(not(target_os = "redox"))]
+        let body = {
+            let mut body = vec![0u8; body_size];
+            self.stream.read_exact(&mut body)?;
+            body
+        };
+
+        #[cfg(target_os = "redox")]
+        let (body, header_fds) = {
+            let fd_count = desc.map_or(0, |desc| desc.iter().filter(|k| **k == wire::ArgKind::Fd).count());
+            transport::recv_with_fds(&mut self.stream, body_size, fd_count)?
+        };
+
+        let interface = match interface {
+            Some(interface) => interface,
+            None => {
+                println!("Unknown object: id={}, opcode={}", obj_id, opcode);
+                return Ok(true);
+            }
+        };
+
+        let args = match desc {
+            Some(desc) => {
+                let mut msg = Message::decode(obj_id, opcode, &body, desc)?;
+                let mut fds = header_fds.into_iter();
+                for arg in msg.args.iter_mut() {
+                    if let Argument::Fd(slot) = arg {
+                        *slot = fds.next().unwrap_or(-1);
+                    }
+                }
+                msg.args
+            }
+            None => Vec::new(),
+        };
 
-        match obj_type.as_deref() {
-            Some("wl_registry") => {
-                if opcode == 0 {
-                    let name = u32::from_ne_bytes([body[0], body[1], body[2], body[3]]);
+        self.handle_builtin_event(&interface, obj_id, opcode, &args)?;
 
-                    let mut interface_end = 4;
-                    while interface_end < body.len() && body[interface_end] != 0 {
-                        interface_end += 1;
-                    }
+        if let Some(mut handler) = self.handlers.remove(&obj_id) {
+            handler.event(self, obj_id, opcode, &args);
+            self.handlers.insert(obj_id, handler);
+        }
 
-                    let interface = String::from_utf8_lossy(&body[4..interface_end]).to_string();
-
-                    let version_start = (interface_end + 4) & !3;
-                    let version = if version_start + 4 <= body.len() {
-                        u32::from_ne_bytes([
-                            body[version_start],
-                            body[version_start + 1],
-                            body[version_start + 2],
-                            body[version_start + 3],
-                        ])
-                    } else {
-                        0
-                    };
-
-                    self.registry_interfaces
-                        .insert(name, (interface.clone(), version));
-                    println!("Global: {}(name: {}, ver: {})", interface, name, version);
-                }
+        Ok(true)
+    }
+
+    /// Protocol bookkeeping that the client itself depends on (tracking
+    /// globals, acking configures, answering pings) and that therefore
+    /// runs regardless of whether the application registered its own
+    /// [`Dispatch`] handler for the object.
+    fn handle_builtin_event(
+        &mut self,
+        interface: &str,
+        obj_id: u32,
+        opcode: u16,
+        args: &[Argument],
+    ) -> Result<(), ClientError> {
+        match (interface, opcode) {
+            ("wl_display", protocol::WL_DISPLAY_ERROR) => {
+                return Err(ClientError::Protocol(WaylandError {
+                    object_id: args[0].as_uint(),
+                    code: args[1].as_uint(),
+                    message: args[2].as_str().to_string(),
+                }));
             }
-            Some("wl_callback") => {
-                if opcode == 0 {
-                    println!("Sync callback received");
-                }
+            ("wl_display", protocol::WL_DISPLAY_DELETE_ID) => {
+                let freed_id = args[0].as_uint();
+                self.objects.remove(&freed_id);
+                self.free_ids.push(freed_id);
             }
-            _ => {
-                println!("Unknown object: id={}, opcode={}", obj_id, opcode);
+            ("wl_registry", protocol::WL_REGISTRY_GLOBAL) => {
+                let name = args[0].as_uint();
+                let iface = args[1].as_str().to_string();
+                let version = args[2].as_uint();
+
+                self.registry_interfaces.insert(name, (iface.clone(), version));
+                println!("Global: {}(name: {}, ver: {})", iface, name, version);
+            }
+            ("wl_callback", protocol::WL_CALLBACK_DONE) => {
+                println!("Sync callback received");
+                self.completed_callbacks.insert(obj_id);
+            }
+            ("xdg_surface", protocol::XDG_SURFACE_CONFIGURE) => {
+                let serial = args[0].as_uint();
+
+                self.configure_serials.insert(obj_id, serial);
+                self.ack_configure(obj_id, serial)?;
             }
+            ("xdg_wm_base", protocol::XDG_WM_BASE_PING) => {
+                let serial = args[0].as_uint();
+
+                let pong = Message::new(obj_id, protocol::XDG_WM_BASE_PONG, vec![Argument::Uint(serial)]);
+                transport::send_message(&mut self.stream, &pong)?;
+            }
+            _ => {}
         }
 
-        Ok(true)
+        Ok(())
     }
 
     fn print_info(&self) {
@@ -163,6 +433,68 @@ fn from_syscall_error(error: syscall::Error) -> io::Error {
     io::Error::from_raw_os_error(error.errno as i32)
 }
 
+/// Logs `wl_pointer`/`wl_keyboard` events to stdout, showing how an
+/// application plugs into the dispatch layer instead of editing
+/// `process_message` directly. `wl_pointer` and `wl_keyboard` opcodes
+/// overlap numerically, so each handler is bound to one object at
+/// registration time (see [`WaylandClient::set_handler`]) rather than
+/// told apart by `object_id` inside `event`.
+enum InputLogger {
+    Pointer,
+    Keyboard,
+}
+
+impl Dispatch for InputLogger {
+    fn event(&mut self, _client: &mut WaylandClient, object_id: u32, opcode: u16, args: &[Argument]) {
+        match (&self, opcode) {
+            (InputLogger::Pointer, protocol::WL_POINTER_MOTION) => {
+                println!(
+                    "pointer {}: motion to ({:.2}, {:.2})",
+                    object_id,
+                    args[1].as_fixed_f64(),
+                    args[2].as_fixed_f64()
+                );
+            }
+            (InputLogger::Pointer, protocol::WL_POINTER_BUTTON) => {
+                println!(
+                    "pointer {}: button {} state {}",
+                    object_id,
+                    args[2].as_uint(),
+                    args[3].as_uint()
+                );
+            }
+            (InputLogger::Keyboard, protocol::WL_KEYBOARD_KEY) => {
+                println!("keyboard {}: key {}", object_id, args[2].as_uint());
+            }
+            (InputLogger::Keyboard, protocol::WL_KEYBOARD_KEYMAP) => {
+                println!(
+                    "keyboard {}: keymap fd {} ({} bytes)",
+                    object_id,
+                    args[1].as_fd(),
+                    args[2].as_uint()
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Creates and immediately unlinks a backing file for a `wl_shm_pool` of
+/// `size` bytes, returning the still-open handle whose fd is handed to the
+/// compositor.
+fn create_anonymous_shm_file(xdg_runtime_dir: &str, size: i32) -> io::Result<File> {
+    let path = Path::new(xdg_runtime_dir).join(format!("wl_shm-{}", std::process::id()));
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)?;
+    file.set_len(size as u64)?;
+    let _ = std::fs::remove_file(&path);
+    Ok(file)
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let xdg_runtime_dir =
         env::var("XDG_RUNTIME_DIR").unwrap_or_else(|_| "/tmp/redox-wayland-99".to_string());
@@ -181,20 +513,38 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("Connected to Wayland server");
 
-    let _callback_id = client.send_sync()?;
-
     let _registry_id = client.get_registry()?;
+    client.roundtrip()?;
+
+    client.print_info();
 
-    let mut count = 0;
-    while count < 20 {
-        if !client.process_message()? {
-            println!("Server closed connection");
-            break;
+    if let (Ok(compositor_id), Ok(wm_base_id)) = (
+        client.bind_global("wl_compositor", 4),
+        client.bind_global("xdg_wm_base", 1),
+    ) {
+        let surface_id = client.create_surface(compositor_id)?;
+        let xdg_surface_id = client.get_xdg_surface(wm_base_id, surface_id)?;
+        let _toplevel_id = client.get_toplevel(xdg_surface_id)?;
+
+        if let Ok(shm_id) = client.bind_global("wl_shm", 1) {
+            let pool_size = 4096;
+            let pool_file = create_anonymous_shm_file(&xdg_runtime_dir, pool_size)?;
+            let _pool_id = client.create_shm_pool(shm_id, pool_file.as_raw_fd(), pool_size)?;
         }
-        count += 1;
-    }
 
-    client.print_info();
+        if let Ok(seat_id) = client.bind_global("wl_seat", 7) {
+            let pointer_id = client.get_pointer(seat_id)?;
+            let keyboard_id = client.get_keyboard(seat_id)?;
+            client.set_handler(pointer_id, Box::new(InputLogger::Pointer));
+            client.set_handler(keyboard_id, Box::new(InputLogger::Keyboard));
+        }
+
+        client.roundtrip()?;
+
+        if let Some(serial) = client.configure_serial(xdg_surface_id) {
+            println!("xdg_surface {} configured with serial {}", xdg_surface_id, serial);
+        }
+    }
 
     Ok(())
 }
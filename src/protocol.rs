@@ -0,0 +1,99 @@
+//! Opcode and argument-signature tables for the Wayland interfaces this
+//! client knows about. Centralizing them here lets the generic dispatch
+//! path in [`crate::WaylandClient::process_message`] decode any event
+//! without interface-specific parsing code.
+
+use crate::wire::{ArgKind, MessageDesc};
+
+// wl_display
+pub const WL_DISPLAY_SYNC: u16 = 0;
+pub const WL_DISPLAY_GET_REGISTRY: u16 = 1;
+pub const WL_DISPLAY_ERROR: u16 = 0;
+pub const WL_DISPLAY_ERROR_DESC: MessageDesc = &[ArgKind::Object, ArgKind::Uint, ArgKind::Str];
+pub const WL_DISPLAY_DELETE_ID: u16 = 1;
+pub const WL_DISPLAY_DELETE_ID_DESC: MessageDesc = &[ArgKind::Uint];
+
+// wl_registry
+pub const WL_REGISTRY_BIND: u16 = 0;
+pub const WL_REGISTRY_GLOBAL: u16 = 0;
+pub const WL_REGISTRY_GLOBAL_DESC: MessageDesc = &[ArgKind::Uint, ArgKind::Str, ArgKind::Uint];
+
+// wl_callback
+pub const WL_CALLBACK_DONE: u16 = 0;
+pub const WL_CALLBACK_DONE_DESC: MessageDesc = &[ArgKind::Uint];
+
+// wl_shm
+pub const WL_SHM_CREATE_POOL: u16 = 0;
+
+// wl_compositor
+pub const WL_COMPOSITOR_CREATE_SURFACE: u16 = 0;
+
+// xdg_wm_base
+pub const XDG_WM_BASE_GET_XDG_SURFACE: u16 = 2;
+pub const XDG_WM_BASE_PONG: u16 = 3;
+pub const XDG_WM_BASE_PING: u16 = 0;
+pub const XDG_WM_BASE_PING_DESC: MessageDesc = &[ArgKind::Uint];
+
+// xdg_surface
+pub const XDG_SURFACE_GET_TOPLEVEL: u16 = 1;
+pub const XDG_SURFACE_ACK_CONFIGURE: u16 = 4;
+pub const XDG_SURFACE_CONFIGURE: u16 = 0;
+pub const XDG_SURFACE_CONFIGURE_DESC: MessageDesc = &[ArgKind::Uint];
+
+// wl_seat
+pub const WL_SEAT_GET_POINTER: u16 = 0;
+pub const WL_SEAT_GET_KEYBOARD: u16 = 1;
+
+// wl_pointer
+pub const WL_POINTER_ENTER: u16 = 0;
+pub const WL_POINTER_ENTER_DESC: MessageDesc =
+    &[ArgKind::Uint, ArgKind::Object, ArgKind::Fixed, ArgKind::Fixed];
+pub const WL_POINTER_LEAVE: u16 = 1;
+pub const WL_POINTER_LEAVE_DESC: MessageDesc = &[ArgKind::Uint, ArgKind::Object];
+pub const WL_POINTER_MOTION: u16 = 2;
+pub const WL_POINTER_MOTION_DESC: MessageDesc = &[ArgKind::Uint, ArgKind::Fixed, ArgKind::Fixed];
+pub const WL_POINTER_BUTTON: u16 = 3;
+pub const WL_POINTER_BUTTON_DESC: MessageDesc =
+    &[ArgKind::Uint, ArgKind::Uint, ArgKind::Uint, ArgKind::Uint];
+pub const WL_POINTER_AXIS: u16 = 4;
+pub const WL_POINTER_AXIS_DESC: MessageDesc = &[ArgKind::Uint, ArgKind::Uint, ArgKind::Fixed];
+
+// wl_keyboard
+pub const WL_KEYBOARD_KEYMAP: u16 = 0;
+pub const WL_KEYBOARD_KEYMAP_DESC: MessageDesc = &[ArgKind::Uint, ArgKind::Fd, ArgKind::Uint];
+pub const WL_KEYBOARD_ENTER: u16 = 1;
+pub const WL_KEYBOARD_ENTER_DESC: MessageDesc = &[ArgKind::Uint, ArgKind::Object, ArgKind::Array];
+pub const WL_KEYBOARD_LEAVE: u16 = 2;
+pub const WL_KEYBOARD_LEAVE_DESC: MessageDesc = &[ArgKind::Uint, ArgKind::Object];
+pub const WL_KEYBOARD_KEY: u16 = 3;
+pub const WL_KEYBOARD_KEY_DESC: MessageDesc =
+    &[ArgKind::Uint, ArgKind::Uint, ArgKind::Uint, ArgKind::Uint];
+pub const WL_KEYBOARD_MODIFIERS: u16 = 4;
+pub const WL_KEYBOARD_MODIFIERS_DESC: MessageDesc =
+    &[ArgKind::Uint, ArgKind::Uint, ArgKind::Uint, ArgKind::Uint, ArgKind::Uint];
+
+/// Looks up the argument signature for an event on `interface`/`opcode`.
+/// Returns `None` for interfaces or opcodes this client doesn't decode
+/// yet, in which case the event is handed to a registered handler (if
+/// any) with no arguments.
+pub fn event_desc(interface: &str, opcode: u16) -> Option<MessageDesc> {
+    match (interface, opcode) {
+        ("wl_display", WL_DISPLAY_ERROR) => Some(WL_DISPLAY_ERROR_DESC),
+        ("wl_display", WL_DISPLAY_DELETE_ID) => Some(WL_DISPLAY_DELETE_ID_DESC),
+        ("wl_registry", WL_REGISTRY_GLOBAL) => Some(WL_REGISTRY_GLOBAL_DESC),
+        ("wl_callback", WL_CALLBACK_DONE) => Some(WL_CALLBACK_DONE_DESC),
+        ("xdg_wm_base", XDG_WM_BASE_PING) => Some(XDG_WM_BASE_PING_DESC),
+        ("xdg_surface", XDG_SURFACE_CONFIGURE) => Some(XDG_SURFACE_CONFIGURE_DESC),
+        ("wl_pointer", WL_POINTER_ENTER) => Some(WL_POINTER_ENTER_DESC),
+        ("wl_pointer", WL_POINTER_LEAVE) => Some(WL_POINTER_LEAVE_DESC),
+        ("wl_pointer", WL_POINTER_MOTION) => Some(WL_POINTER_MOTION_DESC),
+        ("wl_pointer", WL_POINTER_BUTTON) => Some(WL_POINTER_BUTTON_DESC),
+        ("wl_pointer", WL_POINTER_AXIS) => Some(WL_POINTER_AXIS_DESC),
+        ("wl_keyboard", WL_KEYBOARD_KEYMAP) => Some(WL_KEYBOARD_KEYMAP_DESC),
+        ("wl_keyboard", WL_KEYBOARD_ENTER) => Some(WL_KEYBOARD_ENTER_DESC),
+        ("wl_keyboard", WL_KEYBOARD_LEAVE) => Some(WL_KEYBOARD_LEAVE_DESC),
+        ("wl_keyboard", WL_KEYBOARD_KEY) => Some(WL_KEYBOARD_KEY_DESC),
+        ("wl_keyboard", WL_KEYBOARD_MODIFIERS) => Some(WL_KEYBOARD_MODIFIERS_DESC),
+        _ => None,
+    }
+}
@@ -0,0 +1,221 @@
+//! Platform transports for delivering Wayland messages, including the
+//! out-of-band file descriptors carried by `Fd` arguments (e.g.
+//! `wl_shm::create_pool`, `wl_keyboard::keymap`).
+//!
+//! The framed data — the 8-byte header plus packed arguments — travels the
+//! same way everywhere; only how a raw fd crosses into the compositor's
+//! process differs. A normal Unix domain socket uses `sendmsg` with
+//! `SCM_RIGHTS` ancillary data, while Redox's `/scheme/chan` transport has
+//! no socket-level ancillary data and instead transfers the descriptor
+//! through the scheme's `dup` call.
+
+use std::fs::File;
+use std::io::{self, Write};
+#[cfg(target_os = "redox")]
+use std::io::Read;
+use std::os::unix::io::{AsRawFd, RawFd};
+
+use crate::wire::Message;
+
+/// The largest number of `Fd` arguments any single message in this
+/// client's protocol tables carries (see [`crate::protocol`]). The
+/// header read in [`crate::WaylandClient::process_message`] reserves
+/// ancillary-data space for this many fds up front, since on a stream
+/// transport they arrive attached to the header bytes rather than the
+/// body that actually declares them.
+pub const MAX_FDS_PER_MESSAGE: usize = 1;
+
+/// Writes `msg` to `stream`, sending any `Fd` arguments out-of-band.
+pub fn send_message(stream: &mut File, msg: &Message) -> io::Result<()> {
+    let bytes = msg.encode();
+    let fds = msg.fds();
+
+    if fds.is_empty() {
+        return stream.write_all(&bytes);
+    }
+
+    #[cfg(target_os = "redox")]
+    {
+        stream.write_all(&bytes)?;
+        for fd in fds {
+            transfer_fd(stream, fd)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "redox"))]
+    {
+        send_with_scm_rights(stream, &bytes, &fds)
+    }
+}
+
+#[cfg(not(target_os = "redox"))]
+fn send_with_scm_rights(stream: &File, bytes: &[u8], fds: &[RawFd]) -> io::Result<()> {
+    let iov = libc::iovec {
+        iov_base: bytes.as_ptr() as *mut _,
+        iov_len: bytes.len(),
+    };
+
+    let cmsg_space =
+        unsafe { libc::CMSG_SPACE((fds.len() * std::mem::size_of::<RawFd>()) as u32) } as usize;
+    let mut cmsg_buf = vec![0u8; cmsg_space];
+
+    let mut msghdr: libc::msghdr = unsafe { std::mem::zeroed() };
+    msghdr.msg_iov = &iov as *const libc::iovec as *mut libc::iovec;
+    msghdr.msg_iovlen = 1;
+    msghdr.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+    msghdr.msg_controllen = cmsg_space as _;
+
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msghdr);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN((fds.len() * std::mem::size_of::<RawFd>()) as u32) as _;
+        std::ptr::copy_nonoverlapping(fds.as_ptr(), libc::CMSG_DATA(cmsg) as *mut RawFd, fds.len());
+    }
+
+    let sent = unsafe { libc::sendmsg(stream.as_raw_fd(), &msghdr, 0) };
+    if sent < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Transfers `fd` to the compositor through the `chan` scheme's `dup`
+/// call, the Redox equivalent of `SCM_RIGHTS` for this transport.
+#[cfg(target_os = "redox")]
+fn transfer_fd(stream: &File, fd: RawFd) -> io::Result<()> {
+    syscall::dup(stream.as_raw_fd() as usize, format!("fd:{}", fd).as_bytes())
+        .map(|_| ())
+        .map_err(|e| io::Error::from_raw_os_error(e.errno as i32))
+}
+
+/// Resolves one fd transferred by the peer's [`transfer_fd`] into a local
+/// descriptor, by `dup`-ing it off this stream's `chan` handle.
+#[cfg(target_os = "redox")]
+fn receive_fd(stream: &File) -> io::Result<RawFd> {
+    syscall::dup(stream.as_raw_fd() as usize, b"fd")
+        .map(|fd| fd as RawFd)
+        .map_err(|e| io::Error::from_raw_os_error(e.errno as i32))
+}
+
+/// Reads `len` bytes of message body from `stream`, along with up to
+/// `max_fds` file descriptors sent out-of-band alongside it — the
+/// receiving half of the fd passing [`send_message`] does on the way out.
+pub fn recv_with_fds(stream: &mut File, len: usize, max_fds: usize) -> io::Result<(Vec<u8>, Vec<RawFd>)> {
+    #[cfg(target_os = "redox")]
+    {
+        let mut body = vec![0u8; len];
+        stream.read_exact(&mut body)?;
+
+        let mut fds = Vec::with_capacity(max_fds);
+        for _ in 0..max_fds {
+            fds.push(receive_fd(stream)?);
+        }
+        Ok((body, fds))
+    }
+
+    #[cfg(not(target_os = "redox"))]
+    {
+        recv_with_scm_rights(stream, len, max_fds)
+    }
+}
+
+/// Reads exactly `len` bytes via `recvmsg`, collecting up to `max_fds`
+/// out-of-band fds along the way. A single `recvmsg` call can legitimately
+/// return fewer bytes than requested (the same way a plain `read` can), so
+/// this loops until the body is full instead of treating a short read as
+/// an error; `SCM_RIGHTS` data arrives attached to whichever call reads
+/// the first byte of the sender's message, so only that call contributes
+/// to `fds`.
+#[cfg(not(target_os = "redox"))]
+fn recv_with_scm_rights(stream: &File, len: usize, max_fds: usize) -> io::Result<(Vec<u8>, Vec<RawFd>)> {
+    let mut body = vec![0u8; len];
+    let mut fds = Vec::new();
+    let mut filled = 0;
+
+    while filled < len {
+        let iov = libc::iovec {
+            iov_base: body[filled..].as_mut_ptr() as *mut _,
+            iov_len: len - filled,
+        };
+
+        let cmsg_space =
+            unsafe { libc::CMSG_SPACE((max_fds * std::mem::size_of::<RawFd>()) as u32) } as usize;
+        let mut cmsg_buf = vec![0u8; cmsg_space];
+
+        let mut msghdr: libc::msghdr = unsafe { std::mem::zeroed() };
+        msghdr.msg_iov = &iov as *const libc::iovec as *mut libc::iovec;
+        msghdr.msg_iovlen = 1;
+        msghdr.msg_control = cmsg_buf.as_mut_ptr() as *mut libc::c_void;
+        msghdr.msg_controllen = cmsg_space as _;
+
+        let received = unsafe { libc::recvmsg(stream.as_raw_fd(), &mut msghdr, 0) };
+        if received < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if received == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "peer closed the connection mid-message",
+            ));
+        }
+
+        unsafe {
+            let mut cmsg = libc::CMSG_FIRSTHDR(&msghdr);
+            while !cmsg.is_null() {
+                if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                    let data = libc::CMSG_DATA(cmsg) as *const RawFd;
+                    let count = ((*cmsg).cmsg_len as usize - libc::CMSG_LEN(0) as usize)
+                        / std::mem::size_of::<RawFd>();
+                    for i in 0..count {
+                        fds.push(*data.add(i));
+                    }
+                }
+                cmsg = libc::CMSG_NXTHDR(&msghdr, cmsg);
+            }
+        }
+
+        filled += received as usize;
+    }
+
+    Ok((body, fds))
+}
+
+#[cfg(test)]
+#[cfg(not(target_os = "redox"))]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::os::unix::io::{FromRawFd, IntoRawFd};
+    use std::os::unix::net::UnixStream;
+
+    #[test]
+    fn fd_survives_a_round_trip_through_scm_rights() {
+        let (tx, rx) = UnixStream::pair().unwrap();
+        let tx = unsafe { File::from_raw_fd(tx.into_raw_fd()) };
+        let rx = unsafe { File::from_raw_fd(rx.into_raw_fd()) };
+
+        let mut pipe_fds = [0i32; 2];
+        assert_eq!(unsafe { libc::pipe(pipe_fds.as_mut_ptr()) }, 0);
+        let [pipe_read, pipe_write] = pipe_fds;
+
+        let sent_body = b"keymap";
+        send_with_scm_rights(&tx, sent_body, &[pipe_write]).unwrap();
+
+        let (received_body, fds) = recv_with_scm_rights(&rx, sent_body.len(), 1).unwrap();
+        assert_eq!(received_body, sent_body);
+        assert_eq!(fds.len(), 1);
+        assert_ne!(fds[0], pipe_write, "must be a distinct fd, not the same number reused");
+
+        // Prove the received fd is a duplicate of the *same* open file
+        // description as `pipe_write`, not just a valid-looking integer:
+        // writing through it must be visible on the original pipe's read end.
+        let mut received_write = unsafe { File::from_raw_fd(fds[0]) };
+        received_write.write_all(b"ok").unwrap();
+        let mut pipe_reader = unsafe { File::from_raw_fd(pipe_read) };
+        let mut buf = [0u8; 2];
+        pipe_reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"ok");
+    }
+}
@@ -0,0 +1,346 @@
+//! Typed wire format for the Wayland protocol.
+//!
+//! Every request and event on the wire is an 8-byte header (sender id,
+//! then `(size << 16) | opcode`) followed by arguments packed in native
+//! byte order. The wire itself carries no type information, so decoding a
+//! message requires a [`MessageDesc`] describing the argument types for
+//! that object/opcode pair, supplied by the caller.
+
+use std::io;
+use std::os::unix::io::RawFd;
+
+/// The type of a single request/event argument, as declared by a
+/// protocol's signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgKind {
+    Int,
+    Uint,
+    Fixed,
+    Str,
+    Object,
+    NewId,
+    Array,
+    Fd,
+}
+
+/// Ordered argument types for one opcode, as declared by the protocol.
+pub type MessageDesc = &'static [ArgKind];
+
+/// A single decoded (or to-be-encoded) request/event argument.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Argument {
+    Int(i32),
+    Uint(u32),
+    Fixed(i32),
+    Str(String),
+    Object(u32),
+    NewId(u32),
+    Array(Vec<u8>),
+    Fd(RawFd),
+}
+
+impl Argument {
+    pub fn as_int(&self) -> i32 {
+        match self {
+            Argument::Int(v) => *v,
+            other => panic!("argument is not an int: {:?}", other),
+        }
+    }
+
+    pub fn as_uint(&self) -> u32 {
+        match self {
+            Argument::Uint(v) | Argument::Object(v) | Argument::NewId(v) => *v,
+            other => panic!("argument is not a uint/object/new_id: {:?}", other),
+        }
+    }
+
+    pub fn as_fixed(&self) -> i32 {
+        match self {
+            Argument::Fixed(v) => *v,
+            other => panic!("argument is not fixed: {:?}", other),
+        }
+    }
+
+    /// Decodes a `Fixed` argument's 24.8 signed fixed-point representation
+    /// into a float, as used for e.g. `wl_pointer` surface-local coordinates.
+    pub fn as_fixed_f64(&self) -> f64 {
+        Fixed(self.as_fixed()).to_f64()
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Argument::Str(s) => s,
+            other => panic!("argument is not a string: {:?}", other),
+        }
+    }
+
+    pub fn as_array(&self) -> &[u8] {
+        match self {
+            Argument::Array(a) => a,
+            other => panic!("argument is not an array: {:?}", other),
+        }
+    }
+
+    pub fn as_fd(&self) -> RawFd {
+        match self {
+            Argument::Fd(fd) => *fd,
+            other => panic!("argument is not an fd: {:?}", other),
+        }
+    }
+}
+
+/// A Wayland `wl_fixed_t`: a 24.8 signed fixed-point number, used on the
+/// wire for surface-local coordinates and similar sub-pixel values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fixed(i32);
+
+impl Fixed {
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / 256.0
+    }
+}
+
+/// A Wayland request or event: the object it targets (or came from), the
+/// opcode, and its arguments.
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub sender_id: u32,
+    pub opcode: u16,
+    pub args: Vec<Argument>,
+}
+
+impl Message {
+    pub fn new(sender_id: u32, opcode: u16, args: Vec<Argument>) -> Self {
+        Self {
+            sender_id,
+            opcode,
+            args,
+        }
+    }
+
+    /// Encodes this message's header and arguments in native byte order.
+    /// `Fd` arguments contribute no bytes here; file descriptors travel as
+    /// out-of-band ancillary data, collected separately via [`Message::fds`].
+    pub fn encode(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        for arg in &self.args {
+            match arg {
+                Argument::Int(v) => body.extend_from_slice(&v.to_ne_bytes()),
+                Argument::Uint(v) | Argument::Object(v) | Argument::NewId(v) => {
+                    body.extend_from_slice(&v.to_ne_bytes())
+                }
+                Argument::Fixed(v) => body.extend_from_slice(&v.to_ne_bytes()),
+                Argument::Str(s) => encode_array(&mut body, s.as_bytes(), true),
+                Argument::Array(a) => encode_array(&mut body, a, false),
+                Argument::Fd(_) => {}
+            }
+        }
+
+        let size = 8 + body.len();
+        let mut msg = Vec::with_capacity(size);
+        msg.extend_from_slice(&self.sender_id.to_ne_bytes());
+        msg.extend_from_slice(&(((size as u32) << 16) | self.opcode as u32).to_ne_bytes());
+        msg.extend_from_slice(&body);
+        msg
+    }
+
+    /// File descriptors carried by `Fd` arguments, in argument order.
+    pub fn fds(&self) -> Vec<RawFd> {
+        self.args
+            .iter()
+            .filter_map(|arg| match arg {
+                Argument::Fd(fd) => Some(*fd),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Decodes a message body received for `(sender_id, opcode)` according
+    /// to `desc`. The wire gives no type information, so the caller must
+    /// know the signature in advance (e.g. from a per-interface event
+    /// table).
+    pub fn decode(sender_id: u32, opcode: u16, body: &[u8], desc: MessageDesc) -> io::Result<Self> {
+        let mut args = Vec::with_capacity(desc.len());
+        let mut offset = 0;
+
+        for kind in desc {
+            match kind {
+                ArgKind::Int => {
+                    args.push(Argument::Int(read_u32(body, offset)? as i32));
+                    offset += 4;
+                }
+                ArgKind::Uint => {
+                    args.push(Argument::Uint(read_u32(body, offset)?));
+                    offset += 4;
+                }
+                ArgKind::Object => {
+                    args.push(Argument::Object(read_u32(body, offset)?));
+                    offset += 4;
+                }
+                ArgKind::NewId => {
+                    args.push(Argument::NewId(read_u32(body, offset)?));
+                    offset += 4;
+                }
+                ArgKind::Fixed => {
+                    args.push(Argument::Fixed(read_u32(body, offset)? as i32));
+                    offset += 4;
+                }
+                ArgKind::Str => {
+                    let (bytes, consumed) = read_array(body, offset)?;
+                    let text = String::from_utf8_lossy(&bytes[..bytes.len().saturating_sub(1)]);
+                    args.push(Argument::Str(text.into_owned()));
+                    offset += consumed;
+                }
+                ArgKind::Array => {
+                    let (bytes, consumed) = read_array(body, offset)?;
+                    args.push(Argument::Array(bytes));
+                    offset += consumed;
+                }
+                ArgKind::Fd => {
+                    // Fds travel out-of-band (SCM_RIGHTS ancillary data);
+                    // the transport layer fills the real value in after
+                    // decoding the rest of the message.
+                    args.push(Argument::Fd(-1));
+                }
+            }
+        }
+
+        Ok(Self {
+            sender_id,
+            opcode,
+            args,
+        })
+    }
+}
+
+fn pad4(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+fn read_u32(body: &[u8], offset: usize) -> io::Result<u32> {
+    body.get(offset..offset + 4)
+        .map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "truncated message"))
+}
+
+/// Reads a length-prefixed, 4-byte-padded byte array (the wire
+/// representation shared by `string` and `array` arguments) starting at
+/// `offset`. Returns the raw bytes (including the NUL terminator for
+/// strings) and the total number of bytes consumed, including padding.
+fn read_array(body: &[u8], offset: usize) -> io::Result<(Vec<u8>, usize)> {
+    let len = read_u32(body, offset)? as usize;
+    let start = offset + 4;
+    let end = start + len;
+    let bytes = body
+        .get(start..end)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "truncated array"))?
+        .to_vec();
+    let consumed = 4 + pad4(len);
+    Ok((bytes, consumed))
+}
+
+fn encode_array(out: &mut Vec<u8>, data: &[u8], nul_terminate: bool) {
+    let len = data.len() + if nul_terminate { 1 } else { 0 };
+    out.extend_from_slice(&(len as u32).to_ne_bytes());
+    out.extend_from_slice(data);
+    if nul_terminate {
+        out.push(0);
+    }
+    out.resize(out.len() + (pad4(len) - len), 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_writes_sender_id_then_size_and_opcode_header() {
+        let msg = Message::new(7, 3, vec![Argument::Uint(42)]);
+        let bytes = msg.encode();
+
+        // 8-byte header + one 4-byte uint argument.
+        assert_eq!(bytes.len(), 12);
+        assert_eq!(u32::from_ne_bytes(bytes[0..4].try_into().unwrap()), 7);
+
+        let size_opcode = u32::from_ne_bytes(bytes[4..8].try_into().unwrap());
+        assert_eq!(size_opcode >> 16, 12);
+        assert_eq!((size_opcode & 0xFFFF) as u16, 3);
+
+        assert_eq!(u32::from_ne_bytes(bytes[8..12].try_into().unwrap()), 42);
+    }
+
+    #[test]
+    fn string_round_trips_through_padding_and_nul() {
+        // "abc" -> length-prefix of 4 (3 bytes + NUL), then "abc\0" padded
+        // to a 4-byte boundary (already aligned here).
+        let msg = Message::new(1, 0, vec![Argument::Str("abc".to_string())]);
+        let bytes = msg.encode();
+        let body = &bytes[8..];
+
+        assert_eq!(u32::from_ne_bytes(body[0..4].try_into().unwrap()), 4);
+        assert_eq!(&body[4..8], b"abc\0");
+        assert_eq!(body.len(), 8);
+
+        let decoded = Message::decode(1, 0, body, &[ArgKind::Str]).unwrap();
+        assert_eq!(decoded.args[0].as_str(), "abc");
+    }
+
+    #[test]
+    fn string_requiring_padding_round_trips() {
+        // "ab" -> length-prefix of 3 (2 bytes + NUL), "ab\0" padded with one
+        // extra zero byte to reach a 4-byte boundary.
+        let msg = Message::new(1, 0, vec![Argument::Str("ab".to_string())]);
+        let bytes = msg.encode();
+        let body = &bytes[8..];
+
+        assert_eq!(u32::from_ne_bytes(body[0..4].try_into().unwrap()), 3);
+        assert_eq!(&body[4..8], [b'a', b'b', 0, 0]);
+
+        let decoded = Message::decode(1, 0, body, &[ArgKind::Str]).unwrap();
+        assert_eq!(decoded.args[0].as_str(), "ab");
+    }
+
+    #[test]
+    fn array_round_trips() {
+        let data = vec![1u8, 2, 3, 4, 5];
+        let msg = Message::new(1, 0, vec![Argument::Array(data.clone())]);
+        let bytes = msg.encode();
+        let body = &bytes[8..];
+
+        let decoded = Message::decode(1, 0, body, &[ArgKind::Array]).unwrap();
+        assert_eq!(decoded.args[0].as_array(), data.as_slice());
+    }
+
+    #[test]
+    fn int_and_fd_accessors_unwrap_their_variant() {
+        assert_eq!(Argument::Int(-7).as_int(), -7);
+        assert_eq!(Argument::Fd(3).as_fd(), 3);
+    }
+
+    #[test]
+    fn fixed_decodes_24_8_signed_value() {
+        let positive = Argument::Fixed(256 * 3 + 128); // 3.5
+        assert_eq!(positive.as_fixed_f64(), 3.5);
+
+        let negative = Argument::Fixed(-256 * 2 - 128); // -2.5
+        assert_eq!(negative.as_fixed_f64(), -2.5);
+    }
+
+    #[test]
+    fn read_u32_on_truncated_body_is_unexpected_eof_not_a_panic() {
+        let body = [0u8; 2];
+        let err = Message::decode(1, 0, &body, &[ArgKind::Uint]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn read_array_on_truncated_body_is_unexpected_eof_not_a_panic() {
+        // Claims a 10-byte array but the body only has 4 bytes after the
+        // length prefix.
+        let mut body = 10u32.to_ne_bytes().to_vec();
+        body.extend_from_slice(&[0u8; 4]);
+
+        let err = Message::decode(1, 0, &body, &[ArgKind::Array]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}